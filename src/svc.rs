@@ -1,4 +1,4 @@
-//! [`Service`] and [`BoxService`] traits.
+//! The [`Service`] trait, the [`BoxService`] type, and the combinators built on top of them.
 
 use std::pin::Pin;
 use std::sync::Arc;
@@ -12,6 +12,18 @@ pub trait Service<Request>: Sized + Send + Sync + 'static {
     /// The type of error returned by the service.
     type Error: Send + 'static;
 
+    /// Returns `Ok(())` once this [`Service`] is ready to accept a request,
+    /// or `Err` if the service can no longer serve requests.
+    ///
+    /// Services that are always ready (the common case) can rely on the
+    /// default implementation. Services backed by a limited resource
+    /// (e.g. a connection pool, a rate limiter, a concurrency limiter)
+    /// should override this to signal backpressure to their caller
+    /// _before_ a request is committed to [`Service::serve`].
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+        async { Ok(()) }
+    }
+
     /// Serve a response or error for the given request,
     /// using the given context.
     fn serve(
@@ -32,6 +44,11 @@ where
     type Response = S::Response;
     type Error = S::Error;
 
+    #[inline]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+        self.as_ref().poll_ready()
+    }
+
     #[inline]
     fn serve(
         &self,
@@ -48,6 +65,11 @@ where
     type Response = S::Response;
     type Error = S::Error;
 
+    #[inline(always)]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+        (**self).poll_ready()
+    }
+
     #[inline(always)]
     fn serve(
         &self,
@@ -64,6 +86,11 @@ where
     type Response = S::Response;
     type Error = S::Error;
 
+    #[inline]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+        self.as_ref().poll_ready()
+    }
+
     #[inline]
     fn serve(
         &self,
@@ -81,6 +108,10 @@ trait DynService<Request> {
     type Response;
     type Error;
 
+    fn poll_ready_box(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + '_>>;
+
     #[allow(clippy::type_complexity)]
     fn serve_box(
         &self,
@@ -95,6 +126,12 @@ where
     type Response = T::Response;
     type Error = T::Error;
 
+    fn poll_ready_box(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + '_>> {
+        Box::pin(self.poll_ready())
+    }
+
     fn serve_box(
         &self,
         req: Request,
@@ -145,6 +182,11 @@ where
     type Response = Response;
     type Error = Error;
 
+    #[inline]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+        self.inner.poll_ready_box()
+    }
+
     #[inline]
     fn serve(
         &self,
@@ -159,3 +201,470 @@ where
         self
     }
 }
+
+macro_rules! impl_either {
+    ($name:ident, $($variant:ident),+ $(,)?) => {
+        /// A [`Service`] that dispatches a request to one of a fixed set of inner
+        /// services, chosen at construction time.
+        ///
+        /// Unlike [`BoxService`], no heap allocation or dynamic dispatch is
+        /// involved: each variant keeps its own concrete type and future, so
+        /// this is the cheaper choice whenever the set of possible services is
+        /// small and known up front (e.g. routing to one of a handful of
+        /// endpoint services).
+        #[derive(Debug, Clone)]
+        pub enum $name<$($variant),+> {
+            $(
+                #[allow(missing_docs)]
+                $variant($variant),
+            )+
+        }
+
+        impl<Request, Response, Error, $($variant),+> Service<Request> for $name<$($variant),+>
+        where
+            Request: Send + 'static,
+            Response: Send + 'static,
+            Error: Send + 'static,
+            $($variant: Service<Request, Response = Response, Error = Error>),+
+        {
+            type Response = Response;
+            type Error = Error;
+
+            fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+                async move {
+                    match self {
+                        $(Self::$variant(s) => s.poll_ready().await,)+
+                    }
+                }
+            }
+
+            fn serve(
+                &self,
+                req: Request,
+            ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
+                async move {
+                    match self {
+                        $(Self::$variant(s) => s.serve(req).await,)+
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_either!(Either, A, B);
+impl_either!(Either3, A, B, C);
+impl_either!(Either4, A, B, C, D);
+impl_either!(Either5, A, B, C, D, E);
+impl_either!(Either6, A, B, C, D, E, F);
+impl_either!(Either7, A, B, C, D, E, F, G);
+impl_either!(Either8, A, B, C, D, E, F, G, H);
+impl_either!(Either9, A, B, C, D, E, F, G, H, I);
+
+/// A factory that produces a fresh [`Service`] for each `Target`.
+///
+/// Implemented as a blanket trait for any [`Service<Target>`] whose
+/// [response](Service::Response) is itself a [`Service<Request>`].
+pub trait MakeService<Target, Request>: Service<Target>
+where
+    Self::Response: Service<Request>,
+{
+    /// Create a new [`Service`] for the given target.
+    #[inline]
+    fn make_service(
+        &self,
+        target: Target,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
+        self.serve(target)
+    }
+
+    /// Turn this [`MakeService`] into a plain [`Service<Target>`],
+    /// with the `Request` type it makes services for fixed in the adapter's type.
+    fn into_service(self) -> IntoMakeService<Self, Request>
+    where
+        Self: Sized,
+    {
+        IntoMakeService::new(self)
+    }
+
+    /// Borrow this (`'static`) [`MakeService`] as a plain [`Service<Target>`],
+    /// with the `Request` type it makes services for fixed in the adapter's type.
+    fn as_service(&'static self) -> IntoMakeService<&'static Self, Request>
+    where
+        Self: Sized,
+    {
+        IntoMakeService::new(self)
+    }
+
+    /// Box this [`MakeService`] to allow for dynamic dispatch.
+    fn boxed_make_service(self) -> BoxMakeService<Target, Self::Response, Self::Error>
+    where
+        Self: Sized,
+    {
+        BoxMakeService::new(self)
+    }
+}
+
+impl<M, Target, Request> MakeService<Target, Request> for M
+where
+    M: Service<Target>,
+    M::Response: Service<Request>,
+{
+}
+
+/// Adapter returned by [`MakeService::into_service`] and
+/// [`MakeService::as_service`], fixing the `Request` type parameter in its
+/// own type so a [`MakeService`] can be used anywhere a plain [`Service`] is expected.
+pub struct IntoMakeService<M, Request> {
+    make_service: M,
+    _request: std::marker::PhantomData<fn(Request)>,
+}
+
+impl<M, Request> IntoMakeService<M, Request> {
+    fn new(make_service: M) -> Self {
+        Self {
+            make_service,
+            _request: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<M: Clone, Request> Clone for IntoMakeService<M, Request> {
+    fn clone(&self) -> Self {
+        Self::new(self.make_service.clone())
+    }
+}
+
+impl<M: std::fmt::Debug, Request> std::fmt::Debug for IntoMakeService<M, Request> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IntoMakeService")
+            .field("make_service", &self.make_service)
+            .finish()
+    }
+}
+
+impl<M, Target, Request> Service<Target> for IntoMakeService<M, Request>
+where
+    M: MakeService<Target, Request>,
+    M::Response: Service<Request>,
+    Request: 'static,
+{
+    type Response = M::Response;
+    type Error = M::Error;
+
+    #[inline]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+        self.make_service.poll_ready()
+    }
+
+    #[inline]
+    fn serve(
+        &self,
+        target: Target,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
+        self.make_service.serve(target)
+    }
+}
+
+/// A boxed [`MakeService`], for where you require dynamic dispatch
+/// over a service factory.
+pub struct BoxMakeService<Target, Response, Error> {
+    inner: BoxService<Target, Response, Error>,
+}
+
+impl<Target, Response, Error> Clone for BoxMakeService<Target, Response, Error> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Target, Response, Error> BoxMakeService<Target, Response, Error> {
+    /// Create a new [`BoxMakeService`] from the given [`MakeService`].
+    #[inline]
+    pub fn new<M, Request>(make_service: M) -> Self
+    where
+        M: MakeService<Target, Request, Response = Response, Error = Error>,
+        Response: Service<Request>,
+    {
+        Self {
+            inner: BoxService::new(make_service),
+        }
+    }
+}
+
+impl<Target, Response, Error> std::fmt::Debug for BoxMakeService<Target, Response, Error> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxMakeService").finish()
+    }
+}
+
+impl<Target, Response, Error> Service<Target> for BoxMakeService<Target, Response, Error>
+where
+    Target: 'static,
+    Response: Send + 'static,
+    Error: Send + 'static,
+{
+    type Response = Response;
+    type Error = Error;
+
+    #[inline]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send + '_ {
+        self.inner.poll_ready()
+    }
+
+    #[inline]
+    fn serve(
+        &self,
+        target: Target,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + Send + '_ {
+        self.inner.serve(target)
+    }
+}
+
+/// A `!Send` counterpart to [`Service`], for single-threaded executors.
+///
+/// Mirrors [`Service`] exactly, but drops the `Send`/`Sync` bounds.
+pub trait LocalService<Request>: Sized + 'static {
+    /// The type of response returned by the service.
+    type Response: 'static;
+
+    /// The type of error returned by the service.
+    type Error: 'static;
+
+    /// Returns `Ok(())` once this [`LocalService`] is ready to accept a request.
+    ///
+    /// See [`Service::poll_ready`] for the full contract.
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + '_ {
+        async { Ok(()) }
+    }
+
+    /// Serve a response or error for the given request.
+    fn serve(
+        &self,
+        req: Request,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + '_;
+
+    /// Box this service to allow for dynamic dispatch on a single thread.
+    fn boxed_local(self) -> LocalBoxService<Request, Self::Response, Self::Error> {
+        LocalBoxService::new(self)
+    }
+}
+
+impl<S, Request> LocalService<Request> for std::rc::Rc<S>
+where
+    S: LocalService<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    #[inline]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + '_ {
+        self.as_ref().poll_ready()
+    }
+
+    #[inline]
+    fn serve(
+        &self,
+        req: Request,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + '_ {
+        self.as_ref().serve(req)
+    }
+}
+
+impl<S, Request> LocalService<Request> for &'static S
+where
+    S: LocalService<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    #[inline(always)]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + '_ {
+        (**self).poll_ready()
+    }
+
+    #[inline(always)]
+    fn serve(
+        &self,
+        req: Request,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + '_ {
+        (**self).serve(req)
+    }
+}
+
+impl<S, Request> LocalService<Request> for Box<S>
+where
+    S: LocalService<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    #[inline]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + '_ {
+        self.as_ref().poll_ready()
+    }
+
+    #[inline]
+    fn serve(
+        &self,
+        req: Request,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + '_ {
+        self.as_ref().serve(req)
+    }
+}
+
+/// Internal trait for dynamic dispatch of `!Send` async traits,
+/// the same pattern as [`DynService`] but without the `Send` bound
+/// on the boxed future.
+trait DynLocalService<Request> {
+    type Response;
+    type Error;
+
+    fn poll_ready_box(&self) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + '_>>;
+
+    #[allow(clippy::type_complexity)]
+    fn serve_box(
+        &self,
+        req: Request,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + '_>>;
+}
+
+impl<Request, T> DynLocalService<Request> for T
+where
+    T: LocalService<Request>,
+{
+    type Response = T::Response;
+    type Error = T::Error;
+
+    fn poll_ready_box(&self) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + '_>> {
+        Box::pin(self.poll_ready())
+    }
+
+    fn serve_box(
+        &self,
+        req: Request,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + '_>> {
+        Box::pin(self.serve(req))
+    }
+}
+
+/// A boxed [`LocalService`], to serve requests with on a single thread,
+/// for where you require dynamic dispatch but cannot pay the `Send`
+/// bound that [`BoxService`] requires.
+pub struct LocalBoxService<Request, Response, Error> {
+    inner: std::rc::Rc<dyn DynLocalService<Request, Response = Response, Error = Error> + 'static>,
+}
+
+impl<Request, Response, Error> Clone for LocalBoxService<Request, Response, Error> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Request, Response, Error> LocalBoxService<Request, Response, Error> {
+    /// Create a new [`LocalBoxService`] from the given service.
+    #[inline]
+    pub fn new<T>(service: T) -> Self
+    where
+        T: LocalService<Request, Response = Response, Error = Error>,
+    {
+        Self {
+            inner: std::rc::Rc::new(service),
+        }
+    }
+}
+
+impl<Request, Response, Error> std::fmt::Debug for LocalBoxService<Request, Response, Error> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalBoxService").finish()
+    }
+}
+
+impl<Request, Response, Error> LocalService<Request> for LocalBoxService<Request, Response, Error>
+where
+    Request: 'static,
+    Response: 'static,
+    Error: 'static,
+{
+    type Response = Response;
+    type Error = Error;
+
+    #[inline]
+    fn poll_ready(&self) -> impl Future<Output = Result<(), Self::Error>> + '_ {
+        self.inner.poll_ready_box()
+    }
+
+    #[inline]
+    fn serve(
+        &self,
+        req: Request,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> + '_ {
+        self.inner.serve_box(req)
+    }
+
+    #[inline]
+    fn boxed_local(self) -> Self {
+        self
+    }
+}
+
+/// A [`Layer`] wraps a [`Service`], producing another, typically to add
+/// behaviour (e.g. timeouts, retries, logging) in front of it. Stacking
+/// layers builds up a middleware chain around an inner service.
+pub trait Layer<S> {
+    /// The wrapped service produced by this [`Layer`].
+    type Service;
+
+    /// Wrap the given service, returning the new, wrapped service.
+    fn layer(&self, inner: S) -> Self::Service;
+}
+
+/// A [`Layer`] that boxes the wrapped service into a [`BoxService`],
+/// created via [`BoxService::layer`].
+pub struct BoxLayer<Request, Response, Error> {
+    _marker: std::marker::PhantomData<fn(Request, Response, Error)>,
+}
+
+impl<Request, Response, Error> BoxLayer<Request, Response, Error> {
+    fn new() -> Self {
+        Self {
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<Request, Response, Error> Clone for BoxLayer<Request, Response, Error> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<Request, Response, Error> std::fmt::Debug for BoxLayer<Request, Response, Error> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxLayer").finish()
+    }
+}
+
+impl<S, Request, Response, Error> Layer<S> for BoxLayer<Request, Response, Error>
+where
+    S: Service<Request, Response = Response, Error = Error>,
+{
+    type Service = BoxService<Request, Response, Error>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BoxService::new(inner)
+    }
+}
+
+impl<Request, Response, Error> BoxService<Request, Response, Error> {
+    /// Returns a [`Layer`] that boxes the wrapped service into a [`BoxService`],
+    /// for use inside a stack builder to erase the type mid-chain.
+    #[inline]
+    pub fn layer() -> BoxLayer<Request, Response, Error> {
+        BoxLayer::new()
+    }
+}